@@ -2,77 +2,152 @@ use std::convert::TryInto;
 
 use crate::alu_instruction::ArmModeAluInstruction;
 use crate::bitwise::Bits;
+use crate::bus::{Bus, Memory};
+use crate::cpu_mode::{ExceptionType, Mode};
 use crate::instruction::ArmModeInstruction;
+use crate::thumb_instruction::ThumbModeInstruction;
 use crate::{condition::Condition, cpsr::Cpsr, cpu::Cpu};
 
+// The ARM decode lookup table generated by `build.rs`: a 4096-entry array
+// mapping `bits[27:20] << 4 | bits[7:4]` to its instruction class.
+include!(concat!(env!("OUT_DIR"), "/arm_decode_table.rs"));
+
+/// Maps a banked mode to a slot in the banked-register arrays, or `None`
+/// for User/System which use the main bank.
+const fn bank_index(mode: Mode) -> Option<usize> {
+    match mode {
+        Mode::Fiq => Some(0),
+        Mode::Irq => Some(1),
+        Mode::Supervisor => Some(2),
+        Mode::Abort => Some(3),
+        Mode::Undefined => Some(4),
+        Mode::User | Mode::System => None,
+    }
+}
+
 /// Contains the 16 registers for the CPU, latest (R15) is special because
 /// is the program counter.
+///
+/// R13/R14 are banked per exception mode (plus R8–R12 for FIQ) and each
+/// exception mode has a saved program status register (SPSR). The active
+/// bank is selected transparently from the current [`Mode`].
 #[derive(Default)]
-struct Registers([u32; 16]);
+struct Registers {
+    /// User/System bank, also the live view for R0–R7 and R15.
+    main: [u32; 16],
+    /// FIQ-banked R8–R12.
+    fiq_r8_r12: [u32; 5],
+    /// Banked R13/R14 for Fiq/Irq/Supervisor/Abort/Undefined.
+    banked_r13_r14: [[u32; 2]; 5],
+    /// Saved program status register per banked mode.
+    spsr: [u32; 5],
+    /// The mode whose bank is currently selected.
+    mode: Mode,
+}
 
 impl Registers {
     pub fn program_counter(&self) -> usize {
-        self.0[15].try_into().unwrap()
+        self.main[15].try_into().unwrap()
     }
 
     #[cfg(test)] // TODO: remove cfg when this API will be used at least one in prod code.
     pub fn set_program_counter(&mut self, new_value: u32) {
-        self.0[15] = new_value
+        self.main[15] = new_value
     }
 
     pub fn advance_program_counter(&mut self, bytes: u32) {
-        self.0[15] = self.0[15].wrapping_add(bytes);
+        self.main[15] = self.main[15].wrapping_add(bytes);
     }
 
-    #[allow(clippy::only_used_in_recursion)] // FIXME: Possible bug of clippy?
     pub fn set_register_at(&mut self, reg: usize, new_value: u32) {
-        self.0[reg] = new_value;
+        match reg {
+            8..=12 if self.mode == Mode::Fiq => self.fiq_r8_r12[reg - 8] = new_value,
+            13 | 14 => match bank_index(self.mode) {
+                Some(bank) => self.banked_r13_r14[bank][reg - 13] = new_value,
+                None => self.main[reg] = new_value,
+            },
+            _ => self.main[reg] = new_value,
+        }
+    }
+
+    pub fn register_at(&self, reg: usize) -> u32 {
+        match reg {
+            8..=12 if self.mode == Mode::Fiq => self.fiq_r8_r12[reg - 8],
+            13 | 14 => match bank_index(self.mode) {
+                Some(bank) => self.banked_r13_r14[bank][reg - 13],
+                None => self.main[reg],
+            },
+            _ => self.main[reg],
+        }
+    }
+
+    /// Select the active register bank.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// Read the SPSR of the current mode (zero in User/System).
+    pub fn spsr(&self) -> u32 {
+        bank_index(self.mode).map_or(0, |bank| self.spsr[bank])
     }
 
-    pub const fn register_at(&self, reg: usize) -> u32 {
-        self.0[reg]
+    /// Write the SPSR of a given banked mode; a no-op for User/System.
+    pub fn set_spsr(&mut self, mode: Mode, value: u32) {
+        if let Some(bank) = bank_index(mode) {
+            self.spsr[bank] = value;
+        }
+    }
+}
+
+/// The two prefetched opcodes held by the 3-stage fetch–decode–execute
+/// pipeline. `decoded` is the opcode about to be executed, `fetched` the
+/// one queued behind it; together they keep R15 two fetches ahead of the
+/// instruction currently executing (PC+8 in ARM, PC+4 in THUMB) without
+/// any per-instruction adjustment.
+#[derive(Default)]
+struct Pipeline {
+    fetched: Option<u32>,
+    decoded: Option<u32>,
+}
+
+impl Pipeline {
+    /// Discard both prefetched opcodes after a branch or a write to R15;
+    /// the pipeline refills over the following steps.
+    fn flush(&mut self) {
+        self.fetched = None;
+        self.decoded = None;
     }
 }
 
 pub struct Arm7tdmi {
-    rom: Vec<u8>,
+    bus: Memory,
 
     registers: Registers,
     cpsr: Cpsr,
+    pipeline: Pipeline,
 }
 
 const OPCODE_ARM_SIZE: usize = 4;
+const OPCODE_THUMB_SIZE: usize = 2;
 
 impl Cpu for Arm7tdmi {
     type OpCodeType = u32;
     type InstructionType = ArmModeInstruction;
 
     fn fetch(&self) -> Self::OpCodeType {
-        let instruction_index = self.registers.program_counter();
-        let end_instruction = instruction_index + OPCODE_ARM_SIZE;
-        let data_instruction: [u8; 4] = self.rom[instruction_index..end_instruction]
-            .try_into()
-            .expect("`istruction` conversion into [u8; 4]");
-
-        let op_code = u32::from_le_bytes(data_instruction);
-        println!();
-        println!("opcode -> {:b}", op_code);
-
-        op_code
+        self.bus.read_word(self.registers.program_counter())
     }
 
     fn decode(&self, op_code: Self::OpCodeType) -> (Condition, Self::InstructionType) {
         let condition: u8 = (op_code >> 28) // bit 31..=28
             .try_into()
             .expect("conversion `condition` to u8");
-        println!("condition -> {:x}", condition);
 
-        let instruction: ArmModeInstruction = match op_code.try_into() {
-            Ok(instruction) => instruction,
-            Err(e) => todo!("{}", e),
-        };
-
-        println!("instruction -> {:?}", instruction);
+        // Single index into the build-time LUT keyed on the discriminator
+        // bits 27..=20 and 7..=4. The table is total: unhandled encodings
+        // resolve to `Undefined` rather than a panicking default.
+        let key = (((op_code >> 20) & 0xFF) << 4 | ((op_code >> 4) & 0xF)) as usize;
+        let instruction: ArmModeInstruction = ARM_DECODE_TABLE[key];
 
         (condition.into(), instruction)
     }
@@ -86,23 +161,71 @@ impl Cpu for Arm7tdmi {
             BranchLink => {
                 self.branch_link(op_code);
             }
+            BranchAndExchange => {
+                self.branch_and_exchange(op_code);
+            }
             DataProcessing1 | DataProcessing2 | DataProcessing3 => {
                 self.data_processing(op_code);
             }
             DataTransfer => {
                 self.single_data_transfer(op_code);
             }
+            SoftwareInterrupt => {
+                self.software_interrupt(op_code);
+            }
+            Undefined => {
+                self.exception(ExceptionType::Undefined);
+            }
         }
-
-        self.registers.advance_program_counter(4);
     }
 
     fn step(&mut self) {
-        let op_code = self.fetch();
+        // Fetch stage: read at the current PC, which holds the address of
+        // the instruction two fetches ahead of the one about to execute.
+        let op_size = if self.cpsr.thumb() {
+            OPCODE_THUMB_SIZE
+        } else {
+            OPCODE_ARM_SIZE
+        } as u32;
+
+        let fetched = if self.cpsr.thumb() {
+            self.fetch_thumb() as u32
+        } else {
+            self.fetch()
+        };
+
+        let to_execute = self.pipeline.decoded.take();
+        self.pipeline.decoded = self.pipeline.fetched.take();
+        self.pipeline.fetched = Some(fetched);
+
+        // Execute stage: run the opcode that entered the pipeline two
+        // fetches ago, if any (a flushed pipeline leaves a bubble here).
+        // During execute PC still points at the fetch-stage address, so R15
+        // reads as the executing instruction + 8 (ARM) / + 4 (THUMB).
+        let Some(op_code) = to_execute else {
+            // Bubble: nothing to run, but the fetch pointer still advances
+            // so the pipeline refills on the following steps.
+            self.registers.advance_program_counter(op_size);
+            return;
+        };
 
-        let (condition, instruction) = self.decode(op_code);
-        if self.cpsr.can_execute(condition) {
-            self.execute(op_code, instruction)
+        if self.cpsr.thumb() {
+            let instruction = self.decode_thumb(op_code as u16);
+            self.execute_thumb(op_code as u16, instruction);
+        } else {
+            let (condition, instruction) = self.decode(op_code);
+            if self.cpsr.can_execute(condition) {
+                self.execute(op_code, instruction);
+            }
+        }
+
+        // Advance the fetch pointer only when the instruction did not itself
+        // redirect R15: a branch or a write to R15 flushes the pipeline and
+        // leaves PC pointing at the new target, which must be fetched next.
+        // The flush empties `fetched`, so this also holds for a redirect to
+        // the address PC already held (e.g. `mov pc, pc`).
+        if self.pipeline.fetched.is_some() {
+            self.registers.advance_program_counter(op_size);
         }
     }
 }
@@ -110,224 +233,617 @@ impl Cpu for Arm7tdmi {
 impl Arm7tdmi {
     pub(crate) fn new(rom: Vec<u8>) -> Self {
         Self {
-            rom,
+            bus: Memory::new(rom),
             registers: Registers::default(),
             cpsr: Cpsr::default(),
+            pipeline: Pipeline::default(),
         }
     }
 
-    fn branch(&mut self, op_code: u32) {
-        let offset = op_code & 0b0000_0000_1111_1111_1111_1111_1111_1111;
-        println!("offset: {:?}", offset);
+    /// Enter an exception: save CPSR into the target mode's SPSR, stash the
+    /// return address in the banked LR, switch mode and disable bits, force
+    /// ARM state and jump to the exception vector.
+    fn exception(&mut self, kind: ExceptionType) {
+        let target_mode = kind.mode();
+
+        // CPSR → SPSR_<mode>.
+        self.registers.set_spsr(target_mode, self.cpsr.as_u32());
+
+        // LR holds the return address for this exception, whose offset from
+        // R15 depends on the kind and the current instruction width (see
+        // `link_register_offset`).
+        let op_size = if self.cpsr.thumb() {
+            OPCODE_THUMB_SIZE
+        } else {
+            OPCODE_ARM_SIZE
+        } as i32;
+        let return_address = self
+            .registers
+            .register_at(15)
+            .wrapping_add(kind.link_register_offset(op_size) as u32);
+
+        self.cpsr.set_mode(target_mode);
+        self.registers.set_mode(target_mode);
+        self.registers.set_register_at(14, return_address);
+
+        // IRQs are masked on every entry; FIQs additionally on Reset/FIQ.
+        self.cpsr.set_irq_disable(true);
+        if matches!(kind, ExceptionType::Reset | ExceptionType::Fiq) {
+            self.cpsr.set_fiq_disable(true);
+        }
+        self.cpsr.set_thumb(false);
+
+        self.registers.set_register_at(15, kind.vector_address());
+        self.pipeline.flush();
+    }
+
+    /// `SWI`/`SVC`: trap into Supervisor mode via the software-interrupt
+    /// vector, the way a kernel dispatches a syscall.
+    fn software_interrupt(&mut self, _op_code: u32) {
+        self.exception(ExceptionType::SoftwareInterrupt);
+    }
+
+    /// Read a general-purpose register (R0–R15) through the active bank.
+    pub(crate) fn register(&self, index: usize) -> u32 {
+        self.registers.register_at(index)
+    }
+
+    /// Write a general-purpose register (R0–R15) through the active bank.
+    pub(crate) fn set_register(&mut self, index: usize, value: u32) {
+        self.registers.set_register_at(index, value);
+    }
+
+    /// The raw CPSR word.
+    pub(crate) fn cpsr_bits(&self) -> u32 {
+        self.cpsr.as_u32()
+    }
 
-        self.registers.advance_program_counter(8 + offset * 4);
-        println!("PC: {:?}", self.registers.program_counter());
+    /// Overwrite the CPSR, keeping the register bank in sync with the new
+    /// mode bits.
+    pub(crate) fn set_cpsr_bits(&mut self, value: u32) {
+        self.cpsr.set_raw(value);
+        self.registers.set_mode(Mode::from_bits(value));
+    }
+
+    /// The number of addressable bytes on the bus.
+    pub(crate) fn memory_len(&self) -> usize {
+        self.bus.len()
+    }
+
+    /// Read a byte from the bus.
+    pub(crate) fn read_memory(&self, address: usize) -> u8 {
+        self.bus.read_byte(address)
+    }
+
+    /// Write a byte to the bus.
+    pub(crate) fn write_memory(&mut self, address: usize, value: u8) {
+        self.bus.write_byte(address, value);
+    }
+
+    fn branch(&mut self, op_code: u32) {
+        // 24-bit signed word offset; R15 already reads as this_instr + 8
+        // thanks to the pipeline, so the target is simply R15 + offset*4.
+        let offset = Self::branch_offset(op_code);
+        let pc = self.registers.register_at(15);
+        self.registers
+            .set_register_at(15, pc.wrapping_add(offset as u32));
+        self.pipeline.flush();
     }
 
     fn branch_link(&mut self, op_code: u32) {
-        let pc: u32 = self.registers.program_counter().try_into().unwrap();
-        self.registers.set_register_at(14, pc.wrapping_add(4)); // R14 = LR
+        // LR is the address of the instruction after the branch, i.e.
+        // (this_instr + 8) - 4 == R15 - 4.
+        let pc = self.registers.register_at(15);
+        self.registers.set_register_at(14, pc.wrapping_sub(4)); // R14 = LR
+        self.branch(op_code);
+    }
 
-        let offset = op_code & 0b0000_0000_1111_1111_1111_1111_1111_1111;
-        println!("offset: {:?}", offset);
+    /// Sign-extend the 24-bit branch field and scale it to a byte offset.
+    fn branch_offset(op_code: u32) -> i32 {
+        let raw = op_code.get_bits(0..=23);
+        ((raw << 8) as i32 >> 8) * 4
+    }
 
-        self.registers.advance_program_counter(8 + offset * 4);
-        println!("PC: {:?}", self.registers.program_counter());
+    /// ARM `BX Rn`: branch and exchange. Bit 0 of the target selects the
+    /// instruction set — when set the core switches to THUMB state and the
+    /// real branch target has that bit cleared.
+    fn branch_and_exchange(&mut self, op_code: u32) {
+        let rn = op_code.get_bits(0..=3);
+        let target = self.registers.register_at(rn.try_into().unwrap());
+
+        self.cpsr.set_thumb(target.get_bit(0));
+        self.registers.set_register_at(15, target & 0xFFFF_FFFE);
+        self.pipeline.flush();
     }
 
-    fn data_processing(&mut self, opcode: u32) {
-        // bit [25] is I = Immediate Flag
-        let i: bool = opcode.get_bit(25);
-        // bits [24-21]
-        let alu_opcode = opcode.get_bits(21..=24);
-        // bit [20] is sets condition codes
-        let _s = opcode.get_bit(20);
-        // bits [15-12] are the Rd
-        let rd = opcode.get_bits(12..=15);
-        // bits [19-16] are the Rn
-        let rn = opcode.get_bits(16..=19);
-
-        let op2 = match i {
-            // Register as 2nd Operand
-            false => {
-                // bits [6-5] - Shift Type (0=LSL, 1=LSR, 2=ASR, 3=ROR)
-                let shift_type = opcode.get_bits(5..=6);
-                // bit [4] - is Shift by Register Flag (0=Immediate, 1=Register)
-                let r = opcode.get_bit(4);
-                // bits [0-3] 2nd Operand Register (R0..R15) (including PC=R15)
-                let mut op2 = opcode.get_bits(0..=3);
-
-                match r {
-                    // 0=Immediate, 1=Register
-                    // Shift by amount
-                    false => {
-                        // bits [7-11] - Shift amount
-                        let shift_amount = opcode.get_bits(7..=11);
-                        op2 = self.shift(shift_type, shift_amount, op2);
-                    }
-                    // Shift by register
-                    true => {
-                        // bits [11-8] - Shift register (R0-R14) - only lower 8bit 0-255 used
-                        let rs = opcode.get_bits(8..=11);
-                        let shift_amount = self
-                            .registers
-                            .register_at(rs.try_into().unwrap())
-                            .get_bits(0..=7);
-                        op2 = self.shift_immediate(shift_amount, shift_type, op2);
-                    }
-                };
+    fn fetch_thumb(&self) -> u16 {
+        self.bus.read_halfword(self.registers.program_counter())
+    }
 
-                op2
-            }
-            // Immediate as 2nd Operand
-            true => {
-                // bits [11-8] are ROR-Shift applied to nn
-                let is = opcode.get_bits(8..=11);
-                // bits [7-0] are the immediate value
-                let nn = opcode.get_bits(0..=7);
-
-                // I'm not sure about `* 2`
-                nn.rotate_right(is * 2) // TODO: review "ROR-Shift applied to nn (0-30, in steps of 2)"
-            }
-        };
+    fn decode_thumb(&self, op_code: u16) -> ThumbModeInstruction {
+        // The conversion is total: unknown encodings decode to `Undefined`
+        // and trap, mirroring the ARM decode path.
+        ThumbModeInstruction::try_from(op_code).unwrap_or(ThumbModeInstruction::Undefined)
+    }
 
-        match ArmModeAluInstruction::from(alu_opcode) {
-            ArmModeAluInstruction::Mov => self.mov(rd.try_into().unwrap(), op2),
-            ArmModeAluInstruction::Teq => self.teq(rn, op2),
-            _ => todo!(),
+    fn execute_thumb(&mut self, op_code: u16, instruction_type: ThumbModeInstruction) {
+        use ThumbModeInstruction::*;
+        let op_code = op_code as u32;
+        match instruction_type {
+            MoveShiftedRegister => self.thumb_move_shifted_register(op_code),
+            AddSubtract => self.thumb_add_subtract(op_code),
+            AluImmediate => self.thumb_alu_immediate(op_code),
+            HiRegisterOperation => self.thumb_hi_register_operation(op_code),
+            PcRelativeLoad => self.thumb_pc_relative_load(op_code),
+            ConditionalBranch => self.thumb_conditional_branch(op_code),
+            UnconditionalBranch => self.thumb_unconditional_branch(op_code),
+            SoftwareInterrupt => self.software_interrupt(op_code),
+            Undefined => self.exception(ExceptionType::Undefined),
         }
     }
 
-    fn single_data_transfer(&mut self, opcode: u32) {
-        let immediate = opcode.get_bit(25);
-        let up_down = opcode.get_bit(23);
-
-        // bits [19-16] - Base register
-        let rn = opcode.get_bits(16..=19);
+    /// Format 1: `LSL`/`LSR`/`ASR Rd, Rs, #imm`.
+    fn thumb_move_shifted_register(&mut self, op_code: u32) {
+        let shift_type = op_code.get_bits(11..=12);
+        let offset = op_code.get_bits(6..=10);
+        let rs = op_code.get_bits(3..=5) as usize;
+        let rd = op_code.get_bits(0..=2) as usize;
+
+        let value = self.registers.register_at(rs);
+        let (result, carry) = self.shift(shift_type, offset, value, self.cpsr.carry_flag());
+        self.registers.set_register_at(rd, result);
+        self.cpsr.set_sign_flag(result.is_bit_on(31));
+        self.cpsr.set_zero_flag(result == 0);
+        self.cpsr.set_carry_flag(carry);
+    }
 
-        // 0xF is register of PC
-        let address = if rn == 0xF {
-            let pc: u32 = self.registers.program_counter().try_into().unwrap();
-            pc + 8_u32
+    /// Format 2: `ADD`/`SUB Rd, Rs, Rn|#imm`.
+    fn thumb_add_subtract(&mut self, op_code: u32) {
+        let immediate = op_code.get_bit(10);
+        let is_sub = op_code.get_bit(9);
+        let operand = op_code.get_bits(6..=8);
+        let rs = op_code.get_bits(3..=5) as usize;
+        let rd = op_code.get_bits(0..=2) as usize;
+
+        let lhs = self.registers.register_at(rs);
+        let rhs = if immediate {
+            operand
         } else {
-            self.registers.register_at(rn.try_into().unwrap())
+            self.registers.register_at(operand as usize)
         };
 
-        // bits [15-12] - Source/Destination Register
-        let rd = opcode.get_bits(12..=15);
-
-        let offset: u32 = if immediate {
-            todo!()
+        // Subtraction is `lhs + !rhs + 1`, matching the ARM ALU, so the
+        // carry (no-borrow) and signed-overflow flags come out correct.
+        let (result, carry, overflow) = if is_sub {
+            Self::add_with_carry(lhs, !rhs, true)
         } else {
-            opcode.get_bits(0..=11)
+            Self::add_with_carry(lhs, rhs, false)
         };
+        self.registers.set_register_at(rd, result);
+        self.cpsr.set_sign_flag(result.is_bit_on(31));
+        self.cpsr.set_zero_flag(result == 0);
+        self.cpsr.set_carry_flag(carry);
+        self.cpsr.set_overflow_flag(overflow);
+    }
 
-        let load_store: SingleDataTransfer =
-            opcode.try_into().expect("convert to Single Data Transfer");
+    /// Format 3: `MOV`/`CMP`/`ADD`/`SUB Rd, #imm8`.
+    fn thumb_alu_immediate(&mut self, op_code: u32) {
+        let sub_opcode = op_code.get_bits(11..=12);
+        let rd = op_code.get_bits(8..=10) as usize;
+        let offset = op_code.get_bits(0..=7);
+
+        let rd_value = self.registers.register_at(rd);
+        // `arithmetic` carries the (carry, overflow) pair for the ADD/SUB/CMP
+        // forms; MOV is a plain move and leaves C/V untouched.
+        let (result, arithmetic): (u32, Option<(bool, bool)>) = match sub_opcode {
+            // MOV
+            0 => (offset, None),
+            // CMP
+            1 => {
+                let (r, c, v) = Self::add_with_carry(rd_value, !offset, true);
+                (r, Some((c, v)))
+            }
+            // ADD
+            2 => {
+                let (r, c, v) = Self::add_with_carry(rd_value, offset, false);
+                (r, Some((c, v)))
+            }
+            // SUB
+            3 => {
+                let (r, c, v) = Self::add_with_carry(rd_value, !offset, true);
+                (r, Some((c, v)))
+            }
+            _ => unreachable!(),
+        };
 
-        match load_store {
-            SingleDataTransfer::Ldr => self.registers.set_register_at(
-                rd.try_into().unwrap(),
-                if up_down {
-                    address.wrapping_sub(offset)
-                } else {
-                    address.wrapping_add(offset)
-                },
-            ),
-            _ => todo!(),
+        // CMP is the only one that does not write back.
+        if sub_opcode != 1 {
+            self.registers.set_register_at(rd, result);
+        }
+        self.cpsr.set_sign_flag(result.is_bit_on(31));
+        self.cpsr.set_zero_flag(result == 0);
+        if let Some((carry, overflow)) = arithmetic {
+            self.cpsr.set_carry_flag(carry);
+            self.cpsr.set_overflow_flag(overflow);
         }
     }
 
-    fn mov(&mut self, rd: usize, op2: u32) {
-        self.registers.set_register_at(rd, op2);
+    /// Format 5: hi-register `ADD`/`CMP`/`MOV` and `BX`.
+    fn thumb_hi_register_operation(&mut self, op_code: u32) {
+        let sub_opcode = op_code.get_bits(8..=9);
+        let h1 = op_code.get_bit(7);
+        let h2 = op_code.get_bit(6);
+        let rs = op_code.get_bits(3..=5) as usize + if h2 { 8 } else { 0 };
+        let rd = op_code.get_bits(0..=2) as usize + if h1 { 8 } else { 0 };
+
+        let rs_value = self.registers.register_at(rs);
+        match sub_opcode {
+            // ADD
+            0 => {
+                let result = self.registers.register_at(rd).wrapping_add(rs_value);
+                self.registers.set_register_at(rd, result);
+                if rd == 15 {
+                    self.pipeline.flush();
+                }
+            }
+            // CMP
+            1 => {
+                let (result, carry, overflow) =
+                    Self::add_with_carry(self.registers.register_at(rd), !rs_value, true);
+                self.cpsr.set_sign_flag(result.is_bit_on(31));
+                self.cpsr.set_zero_flag(result == 0);
+                self.cpsr.set_carry_flag(carry);
+                self.cpsr.set_overflow_flag(overflow);
+            }
+            // MOV
+            2 => {
+                self.registers.set_register_at(rd, rs_value);
+                if rd == 15 {
+                    self.pipeline.flush();
+                }
+            }
+            // BX
+            3 => {
+                self.cpsr.set_thumb(rs_value.get_bit(0));
+                self.registers.set_register_at(15, rs_value & 0xFFFF_FFFE);
+                self.pipeline.flush();
+            }
+            _ => unreachable!(),
+        }
     }
 
-    fn teq(&mut self, rn: u32, op2: u32) {
-        let value = self.registers.register_at(rn.try_into().unwrap()) ^ op2;
-        self.cpsr.set_sign_flag(value.is_bit_on(31));
-        self.cpsr.set_zero_flag(value == 0);
+    /// Format 6: `LDR Rd, [PC, #imm8*4]`.
+    fn thumb_pc_relative_load(&mut self, op_code: u32) {
+        let rd = op_code.get_bits(8..=10) as usize;
+        let offset = op_code.get_bits(0..=7) * 4;
+
+        // R15 already reads as PC+4 via the pipeline; bit 1 is forced to 0.
+        let base = self.registers.register_at(15) & 0xFFFF_FFFC;
+        let address = (base + offset) as usize;
+
+        let value = self.bus.read_word(address);
+        self.registers.set_register_at(rd, value);
+    }
+
+    /// Format 16: `B<cond> label`.
+    fn thumb_conditional_branch(&mut self, op_code: u32) {
+        let condition: u8 = op_code.get_bits(8..=11) as u8;
+
+        if self.cpsr.can_execute(condition.into()) {
+            let offset = ((op_code.get_bits(0..=7) as u8 as i8) as i32) * 2;
+            // R15 already reads as PC+4 via the pipeline.
+            let target = (self.registers.register_at(15) as i32).wrapping_add(offset) as u32;
+            self.registers.set_register_at(15, target);
+            self.pipeline.flush();
+        }
     }
 
-    fn shift(&mut self, shift_type: u32, shift_amount: u32, mut value: u32) -> u32 {
-        match shift_amount {
-            0 => match shift_type {
-                // LSL#0: No shift performed, ie. directly value=Rm, the C flag is NOT affected.
-                0 => (), // TODO: It's better to implement the logical instruction in order to execute directly LSL#0?
-                // LSR#0: Interpreted as LSR#32, ie. value becomes zero, C becomes Bit 31 of Rm.
-                1 => {
-                    // TODO: It's better to implement the logical instruction in order to execute directly LSR#0?
-                    let rm = self.registers.register_at(value.try_into().unwrap());
-                    self.cpsr.set_sign_flag(rm.get_bit(31));
-                    value = 0;
+    /// Format 18: `B label`.
+    fn thumb_unconditional_branch(&mut self, op_code: u32) {
+        // 11-bit signed offset, sign-extended and scaled by 2.
+        let raw = op_code.get_bits(0..=10);
+        let offset = (raw << 21) as i32 >> 20;
+        // R15 already reads as PC+4 via the pipeline.
+        let target = (self.registers.register_at(15) as i32).wrapping_add(offset) as u32;
+        self.registers.set_register_at(15, target);
+        self.pipeline.flush();
+    }
+
+    fn data_processing(&mut self, opcode: u32) {
+        use ArmModeAluInstruction::*;
+
+        // bit [25] is I = Immediate Flag
+        let i: bool = opcode.get_bit(25);
+        // bits [24-21] - ALU operation
+        let alu_opcode = opcode.get_bits(21..=24);
+        // bit [20] S - set condition codes
+        let s = opcode.get_bit(20);
+        // bits [15-12] are the Rd
+        let rd = opcode.get_bits(12..=15) as usize;
+        // bits [19-16] are the Rn
+        let rn = self.registers.register_at(opcode.get_bits(16..=19) as usize);
+
+        let carry_in = self.cpsr.carry_flag();
+        let (op2, shifter_carry) = self.operand2(opcode, i, carry_in);
+
+        // Each arm yields the result, whether it is written back, and the
+        // arithmetic (carry, overflow) flags — `None` marks a logical op,
+        // whose carry comes from the barrel shifter instead.
+        let (result, write_result, arithmetic): (u32, bool, Option<(bool, bool)>) =
+            match ArmModeAluInstruction::from(alu_opcode) {
+                And => (rn & op2, true, None),
+                Eor => (rn ^ op2, true, None),
+                Orr => (rn | op2, true, None),
+                Mov => (op2, true, None),
+                Bic => (rn & !op2, true, None),
+                Mvn => (!op2, true, None),
+                Tst => (rn & op2, false, None),
+                Teq => (rn ^ op2, false, None),
+                Add => {
+                    let (r, c, v) = Self::add_with_carry(rn, op2, false);
+                    (r, true, Some((c, v)))
                 }
-                // ASR#0: Interpreted as ASR#32, ie. value and C are filled by Bit 31 of Rm.
-                2 => {
-                    // TODO: It's better to implement the logical instruction in order to execute directly ASR#0?
-                    let rm = self.registers.register_at(value.try_into().unwrap());
-                    match rm.get_bit(31) {
-                        true => {
-                            value = 1;
-                            self.cpsr.set_sign_flag(true)
-                        }
-                        false => {
-                            value = 0;
-                            self.cpsr.set_sign_flag(true)
-                        }
-                    }
+                Adc => {
+                    let (r, c, v) = Self::add_with_carry(rn, op2, carry_in);
+                    (r, true, Some((c, v)))
                 }
-                // ROR#0: Interpreted as RRX#1 (RCR), like ROR#1, but value Bit 31 set to old C.
-                3 => {
-                    // TODO: It's better to implement the logical instruction in order to execute directly RRX#0?
-                    todo!("value Bit 31 set to old C"); // I'm not sure what "old C" means
+                Sub => {
+                    let (r, c, v) = Self::add_with_carry(rn, !op2, true);
+                    (r, true, Some((c, v)))
                 }
-                _ => unreachable!(),
-            },
-            shift_amount => value = self.shift_immediate(shift_type, shift_amount, value),
+                Rsb => {
+                    let (r, c, v) = Self::add_with_carry(op2, !rn, true);
+                    (r, true, Some((c, v)))
+                }
+                Sbc => {
+                    let (r, c, v) = Self::add_with_carry(rn, !op2, carry_in);
+                    (r, true, Some((c, v)))
+                }
+                Rsc => {
+                    let (r, c, v) = Self::add_with_carry(op2, !rn, carry_in);
+                    (r, true, Some((c, v)))
+                }
+                Cmp => {
+                    let (r, c, v) = Self::add_with_carry(rn, !op2, true);
+                    (r, false, Some((c, v)))
+                }
+                Cmn => {
+                    let (r, c, v) = Self::add_with_carry(rn, op2, false);
+                    (r, false, Some((c, v)))
+                }
+            };
+
+        if write_result {
+            self.registers.set_register_at(rd, result);
+            if rd == 15 {
+                self.pipeline.flush();
+            }
+        }
+
+        if s {
+            // `MOVS` (and friends) into R15 restore CPSR from the banked
+            // SPSR of the current mode rather than setting flags piecemeal.
+            if rd == 15 && write_result {
+                let spsr = self.registers.spsr();
+                self.cpsr.set_raw(spsr);
+                self.registers.set_mode(Mode::from_bits(spsr));
+            } else {
+                self.cpsr.set_sign_flag(result.is_bit_on(31));
+                self.cpsr.set_zero_flag(result == 0);
+                match arithmetic {
+                    Some((carry, overflow)) => {
+                        self.cpsr.set_carry_flag(carry);
+                        self.cpsr.set_overflow_flag(overflow);
+                    }
+                    // Logical ops take C from the shifter and leave V alone.
+                    None => self.cpsr.set_carry_flag(shifter_carry),
+                }
+            }
+        }
+    }
+
+    /// Add `a + b + carry_in`, returning the result together with the
+    /// unsigned carry-out and the signed overflow flag. Subtraction is
+    /// expressed as `a + !b + carry_in`, so this covers SUB/SBC/CMP too.
+    fn add_with_carry(a: u32, b: u32, carry_in: bool) -> (u32, bool, bool) {
+        let (partial, carry1) = a.overflowing_add(b);
+        let (result, carry2) = partial.overflowing_add(carry_in as u32);
+        let carry = carry1 || carry2;
+        let overflow = ((a ^ result) & (b ^ result)).is_bit_on(31);
+        (result, carry, overflow)
+    }
+
+    /// Compute the data-processing second operand and its shifter carry-out.
+    fn operand2(&self, opcode: u32, immediate: bool, carry_in: bool) -> (u32, bool) {
+        if immediate {
+            // 8-bit immediate rotated right by twice the 4-bit field.
+            let rotate = opcode.get_bits(8..=11) * 2;
+            let value = opcode.get_bits(0..=7).rotate_right(rotate);
+            let carry = if rotate == 0 {
+                carry_in
+            } else {
+                value.is_bit_on(31)
+            };
+            (value, carry)
+        } else {
+            let shift_type = opcode.get_bits(5..=6);
+            let rm = self.registers.register_at(opcode.get_bits(0..=3) as usize);
+            if opcode.get_bit(4) {
+                // Shift amount from the bottom byte of Rs.
+                let rs = opcode.get_bits(8..=11) as usize;
+                let amount = self.registers.register_at(rs).get_bits(0..=7);
+                self.shift_register(shift_type, amount, rm, carry_in)
+            } else {
+                let amount = opcode.get_bits(7..=11);
+                self.shift(shift_type, amount, rm, carry_in)
+            }
+        }
+    }
+
+    fn single_data_transfer(&mut self, opcode: u32) {
+        // bit [25] I: 0 = 12-bit immediate offset, 1 = shifted register
+        let register_offset = opcode.get_bit(25);
+        // bit [24] P: pre-indexed (add/subtract before the access)
+        let pre_index = opcode.get_bit(24);
+        // bit [23] U: add (1) or subtract (0) the offset
+        let up = opcode.get_bit(23);
+        // bit [22] B: byte (1) or word (0) access
+        let byte = opcode.get_bit(22);
+        // bit [21] W: write the computed address back to the base register
+        let write_back = opcode.get_bit(21);
+        // bit [20] L: load (1) or store (0)
+        let load = opcode.get_bit(20);
+
+        // bits [19-16] - Base register; bits [15-12] - Source/Destination
+        let rn = opcode.get_bits(16..=19) as usize;
+        let rd = opcode.get_bits(12..=15) as usize;
+
+        let offset = if register_offset {
+            let shift_type = opcode.get_bits(5..=6);
+            let shift_amount = opcode.get_bits(7..=11);
+            let rm = self.registers.register_at(opcode.get_bits(0..=3) as usize);
+            self.shift(shift_type, shift_amount, rm, self.cpsr.carry_flag()).0
+        } else {
+            opcode.get_bits(0..=11)
+        };
+
+        // R15 already reads as this_instr + 8 via the pipeline, so the base
+        // address needs no manual adjustment.
+        let base = self.registers.register_at(rn);
+        let offset_address = if up {
+            base.wrapping_add(offset)
+        } else {
+            base.wrapping_sub(offset)
         };
+        let address = if pre_index { offset_address } else { base } as usize;
 
-        value
+        if load {
+            let value = if byte {
+                self.bus.read_byte(address) as u32
+            } else {
+                self.bus.read_word(address)
+            };
+            self.registers.set_register_at(rd, value);
+            if rd == 15 {
+                self.pipeline.flush();
+            }
+        } else {
+            let value = self.registers.register_at(rd);
+            if byte {
+                self.bus.write_byte(address, value as u8);
+            } else {
+                self.bus.write_word(address, value);
+            }
+        }
+
+        // Post-indexing always writes back; pre-indexing only when W is set.
+        if !pre_index || write_back {
+            self.registers.set_register_at(rn, offset_address);
+        }
     }
 
-    fn shift_immediate(&self, shift_type: u32, shift_amount: u32, mut value: u32) -> u32 {
+    /// Barrel-shift `value` by an immediate amount (0..=31), returning the
+    /// shifted value and the carry-out for the C flag. An amount of zero
+    /// encodes the LSL#0/LSR#32/ASR#32/RRX special cases.
+    fn shift(&self, shift_type: u32, shift_amount: u32, value: u32, carry_in: bool) -> (u32, bool) {
         match shift_type {
             // Logical Shift Left
-            0 => value <<= shift_amount,
+            0 => {
+                if shift_amount == 0 {
+                    // LSL#0: value unchanged, C unaffected.
+                    (value, carry_in)
+                } else {
+                    (value << shift_amount, value.is_bit_on(32 - shift_amount))
+                }
+            }
             // Logical Shift Right
-            1 => value >>= shift_amount,
+            1 => {
+                if shift_amount == 0 {
+                    // LSR#0 is interpreted as LSR#32.
+                    (0, value.is_bit_on(31))
+                } else {
+                    (value >> shift_amount, value.is_bit_on(shift_amount - 1))
+                }
+            }
             // Arithmetic Shift Right
-            2 => value = ((value as i32) >> shift_amount) as u32, // TODO: Review rust arithmetic shift right
+            2 => {
+                if shift_amount == 0 {
+                    // ASR#0 is interpreted as ASR#32.
+                    let fill = value.is_bit_on(31);
+                    (if fill { u32::MAX } else { 0 }, fill)
+                } else {
+                    (
+                        ((value as i32) >> shift_amount) as u32,
+                        value.is_bit_on(shift_amount - 1),
+                    )
+                }
+            }
             // Rotate Right
-            3 => value = value.rotate_right(shift_amount as u32),
+            3 => {
+                if shift_amount == 0 {
+                    // ROR#0 is interpreted as RRX: shift right one, filling
+                    // bit 31 with the old carry, and C takes the old bit 0.
+                    let result = (value >> 1) | ((carry_in as u32) << 31);
+                    (result, value.is_bit_on(0))
+                } else {
+                    (value.rotate_right(shift_amount), value.is_bit_on(shift_amount - 1))
+                }
+            }
             _ => unreachable!(),
         }
-
-        value
     }
-}
 
-enum SingleDataTransfer {
-    Ldr,
-    Str,
-    Pld,
-}
+    /// Barrel-shift `value` by a register-supplied amount (0..=255). Unlike
+    /// the immediate form an amount of zero leaves both the value and C
+    /// untouched; for LSL/LSR/ASR, amounts of 32 and beyond saturate to
+    /// all-zero/all-sign, while ROR rotates by the amount modulo 32.
+    fn shift_register(
+        &self,
+        shift_type: u32,
+        shift_amount: u32,
+        value: u32,
+        carry_in: bool,
+    ) -> (u32, bool) {
+        if shift_amount == 0 {
+            return (value, carry_in);
+        }
 
-impl From<u32> for SingleDataTransfer {
-    fn from(op_code: u32) -> Self {
-        // TODO: possible improvements
-        // - op_code.are_bits_on(31..28)
-        // - op_code.is_on(31).and(30).and(29)...
-        let must_for_pld = op_code.is_bit_on(31)
-            && op_code.is_bit_on(30)
-            && op_code.is_bit_on(29)
-            && op_code.is_bit_on(28);
-        if op_code.get_bit(20) {
-            if must_for_pld {
-                Self::Pld
-            } else {
-                Self::Ldr
+        match shift_type {
+            // Logical Shift Left
+            0 => match shift_amount {
+                1..=31 => (value << shift_amount, value.is_bit_on(32 - shift_amount)),
+                32 => (0, value.is_bit_on(0)),
+                _ => (0, false),
+            },
+            // Logical Shift Right
+            1 => match shift_amount {
+                1..=31 => (value >> shift_amount, value.is_bit_on(shift_amount - 1)),
+                32 => (0, value.is_bit_on(31)),
+                _ => (0, false),
+            },
+            // Arithmetic Shift Right
+            2 => {
+                if shift_amount >= 32 {
+                    let fill = value.is_bit_on(31);
+                    (if fill { u32::MAX } else { 0 }, fill)
+                } else {
+                    (
+                        ((value as i32) >> shift_amount) as u32,
+                        value.is_bit_on(shift_amount - 1),
+                    )
+                }
             }
-        } else {
-            Self::Str
+            // Rotate Right
+            3 => {
+                let amount = shift_amount & 0b1_1111;
+                if amount == 0 {
+                    // ROR by a multiple of 32: value unchanged, C = bit 31.
+                    (value, value.is_bit_on(31))
+                } else {
+                    (value.rotate_right(amount), value.is_bit_on(amount - 1))
+                }
+            }
+            _ => unreachable!(),
         }
     }
 }
@@ -356,10 +872,13 @@ mod tests {
     #[test]
     fn test_registers_14_after_branch_link() {
         let mut cpu: Arm7tdmi = Arm7tdmi::new(vec![]);
-        cpu.registers = Registers([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        cpu.registers = Registers {
+            main: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+            ..Default::default()
+        };
         let pc: u32 = cpu.registers.program_counter().try_into().unwrap();
         cpu.branch_link(0b0);
-        assert_eq!(cpu.registers.register_at(14), pc.wrapping_add(4));
+        assert_eq!(cpu.registers.register_at(14), pc.wrapping_sub(4));
     }
 
     #[test]
@@ -386,22 +905,18 @@ mod tests {
             assert_eq!(instruction_type, ArmModeInstruction::DataProcessing3);
 
             cpu.execute(opcode, instruction_type);
+            // `execute` no longer advances PC (the pipeline does), so the
+            // destination register holds exactly the rotated immediate.
             let rotated = rx.rotate_right(is * 2);
-            if rotated == 15 {
-                // NOTE: since is R15 you should also consider the advance of 4 bytes after execution.
-                assert_eq!(
-                    cpu.registers.register_at(rx.try_into().unwrap()),
-                    rotated + 4
-                );
-            } else {
-                assert_eq!(cpu.registers.register_at(rx.try_into().unwrap()), rotated);
-            }
+            assert_eq!(cpu.registers.register_at(rx.try_into().unwrap()), rotated);
         }
     }
 
     #[test]
     fn check_teq() {
-        let op_code: u32 = 0b1110_0001_0010_1001_0011_0000_0000_0000;
+        // TEQS R9, r0: the S bit must be set, otherwise the encoding is the
+        // MRS/MSR (PSR transfer) space rather than a compare.
+        let op_code: u32 = 0b1110_0001_0011_1001_0011_0000_0000_0000;
         let mut cpu = Arm7tdmi::new(vec![]);
 
         let (_, instruction) = cpu.decode(op_code);
@@ -430,12 +945,81 @@ mod tests {
 
         assert_eq!(rd, 13);
 
-        // because in this specific case address will be
-        // then will be 92 + 8 (.wrapping_sub(offset))
+        // LDR R13, [R15, #24] with R15 = 92 loads the word at address 116.
         cpu.registers.set_program_counter(92);
+        cpu.bus.write_word(116, 0xDEAD_BEEF);
+
+        cpu.execute(op_code, instruction);
+        assert_eq!(cpu.registers.register_at(13), 0xDEAD_BEEF);
+    }
+
+    #[test]
+    fn check_adds_sets_carry_and_zero() {
+        // ADDS R2, R0, R1 with R0 = 0xFFFF_FFFF, R1 = 1 -> 0, carry, no overflow.
+        let op_code: u32 = 0b1110_0000_1001_0000_0010_0000_0000_0001;
+        let mut cpu = Arm7tdmi::new(vec![]);
+        cpu.registers.set_register_at(0, 0xFFFF_FFFF);
+        cpu.registers.set_register_at(1, 1);
 
+        let (_, instruction) = cpu.decode(op_code);
         cpu.execute(op_code, instruction);
-        assert_eq!(cpu.registers.register_at(13), 76);
-        assert_eq!(cpu.registers.program_counter(), 96);
+
+        assert_eq!(cpu.registers.register_at(2), 0);
+        assert!(cpu.cpsr.zero_flag());
+        assert!(cpu.cpsr.carry_flag());
+        assert!(!cpu.cpsr.overflow_flag());
+    }
+
+    #[test]
+    fn check_subs_clears_borrow() {
+        // SUBS R2, R0, R1 with R0 = 5, R1 = 3 -> 2, carry set (no borrow).
+        let op_code: u32 = 0b1110_0000_0101_0000_0010_0000_0000_0001;
+        let mut cpu = Arm7tdmi::new(vec![]);
+        cpu.registers.set_register_at(0, 5);
+        cpu.registers.set_register_at(1, 3);
+
+        let (_, instruction) = cpu.decode(op_code);
+        cpu.execute(op_code, instruction);
+
+        assert_eq!(cpu.registers.register_at(2), 2);
+        assert!(!cpu.cpsr.zero_flag());
+        assert!(cpu.cpsr.carry_flag());
+        assert!(!cpu.cpsr.overflow_flag());
+    }
+
+    #[test]
+    fn step_loop_branch_lands_on_target() {
+        // B +2 at address 0 branches to address 16, where `MOV R0, #42`
+        // runs. Driving the pipeline end-to-end must land on the target and
+        // never execute the two words the branch skips over.
+        let mut cpu = Arm7tdmi::new(vec![0; 64]);
+        cpu.bus.write_word(0, 0xEA00_0002); // B #16
+        cpu.bus.write_word(16, 0xE3A0_002A); // MOV R0, #42
+
+        // Fetch(0)/fetch(4)/execute-branch/refill/decode/execute-MOV.
+        for _ in 0..6 {
+            cpu.step();
+        }
+
+        assert_eq!(cpu.registers.register_at(0), 42);
+    }
+
+    #[test]
+    fn check_store_then_load_roundtrip() {
+        // STR R1, [R0] then LDR R2, [R0] over the same address.
+        let str_op: u32 = 0b1110_0101_1000_0000_0001_0000_0000_0000; // STR r1,[r0]
+        let ldr_op: u32 = 0b1110_0101_1001_0000_0010_0000_0000_0000; // LDR r2,[r0]
+
+        let mut cpu = Arm7tdmi::new(vec![0; 64]);
+        cpu.registers.set_register_at(0, 40);
+        cpu.registers.set_register_at(1, 0x1234_5678);
+
+        let (_, store) = cpu.decode(str_op);
+        cpu.execute(str_op, store);
+        assert_eq!(cpu.bus.read_word(40), 0x1234_5678);
+
+        let (_, load) = cpu.decode(ldr_op);
+        cpu.execute(ldr_op, load);
+        assert_eq!(cpu.registers.register_at(2), 0x1234_5678);
     }
 }
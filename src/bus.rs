@@ -0,0 +1,84 @@
+/// An addressable memory bus. Reads and writes are little-endian; word and
+/// halfword reads rotate the result for unaligned addresses the way the
+/// ARM7TDMI does (the access is forced aligned and the fetched value is
+/// rotated right by the misalignment in bytes).
+pub trait Bus {
+    fn read_byte(&self, address: usize) -> u8;
+    fn read_halfword(&self, address: usize) -> u16;
+    fn read_word(&self, address: usize) -> u32;
+
+    fn write_byte(&mut self, address: usize, value: u8);
+    fn write_halfword(&mut self, address: usize, value: u16);
+    fn write_word(&mut self, address: usize, value: u32);
+}
+
+/// A flat little-endian memory, the default backing store for the core.
+#[derive(Default)]
+pub struct Memory {
+    data: Vec<u8>,
+}
+
+impl Memory {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+
+    /// The number of addressable bytes.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether the memory holds no addressable bytes.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl Bus for Memory {
+    // Accesses outside the backing store behave like open bus: reads return
+    // zero and writes are dropped, rather than panicking on an out-of-range
+    // index. The wider word/halfword helpers are built from these so the
+    // whole bus is bounds-safe.
+    fn read_byte(&self, address: usize) -> u8 {
+        self.data.get(address).copied().unwrap_or(0)
+    }
+
+    fn read_halfword(&self, address: usize) -> u16 {
+        // Force halfword alignment and rotate by the dropped bit.
+        let aligned = address & !0b1;
+        let value = u16::from_le_bytes([self.read_byte(aligned), self.read_byte(aligned + 1)]);
+        value.rotate_right((address as u32 & 0b1) * 8)
+    }
+
+    fn read_word(&self, address: usize) -> u32 {
+        // Force word alignment and rotate by the dropped bits.
+        let aligned = address & !0b11;
+        let value = u32::from_le_bytes([
+            self.read_byte(aligned),
+            self.read_byte(aligned + 1),
+            self.read_byte(aligned + 2),
+            self.read_byte(aligned + 3),
+        ]);
+        value.rotate_right((address as u32 & 0b11) * 8)
+    }
+
+    fn write_byte(&mut self, address: usize, value: u8) {
+        if let Some(slot) = self.data.get_mut(address) {
+            *slot = value;
+        }
+    }
+
+    fn write_halfword(&mut self, address: usize, value: u16) {
+        let aligned = address & !0b1;
+        for (offset, byte) in value.to_le_bytes().into_iter().enumerate() {
+            self.write_byte(aligned + offset, byte);
+        }
+    }
+
+    fn write_word(&mut self, address: usize, value: u32) {
+        let aligned = address & !0b11;
+        for (offset, byte) in value.to_le_bytes().into_iter().enumerate() {
+            self.write_byte(aligned + offset, byte);
+        }
+    }
+}
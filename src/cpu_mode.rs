@@ -0,0 +1,85 @@
+/// The seven ARM7TDMI operating modes, encoded as their CPSR mode-bit
+/// values (bits 4..=0). User and System share a single register bank; the
+/// remaining five are exception modes with their own banked R13/R14 and a
+/// saved program status register.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Mode {
+    #[default]
+    User = 0b1_0000,
+    Fiq = 0b1_0001,
+    Irq = 0b1_0010,
+    Supervisor = 0b1_0011,
+    Abort = 0b1_0111,
+    Undefined = 0b1_1011,
+    System = 0b1_1111,
+}
+
+impl Mode {
+    /// Decode the CPSR mode field; unknown encodings fall back to `User`.
+    pub fn from_bits(bits: u32) -> Self {
+        match bits & 0b1_1111 {
+            0b1_0001 => Self::Fiq,
+            0b1_0010 => Self::Irq,
+            0b1_0011 => Self::Supervisor,
+            0b1_0111 => Self::Abort,
+            0b1_1011 => Self::Undefined,
+            0b1_1111 => Self::System,
+            _ => Self::User,
+        }
+    }
+}
+
+/// The exception types the core can enter, in ascending vector order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExceptionType {
+    Reset,
+    Undefined,
+    SoftwareInterrupt,
+    PrefetchAbort,
+    DataAbort,
+    Irq,
+    Fiq,
+}
+
+impl ExceptionType {
+    /// The exception vector address loaded into PC on entry.
+    pub const fn vector_address(self) -> u32 {
+        match self {
+            Self::Reset => 0x00,
+            Self::Undefined => 0x04,
+            Self::SoftwareInterrupt => 0x08,
+            Self::PrefetchAbort => 0x0C,
+            Self::DataAbort => 0x10,
+            Self::Irq => 0x18,
+            Self::Fiq => 0x1C,
+        }
+    }
+
+    /// The offset added to R15 on entry to obtain the address banked into
+    /// LR, expressed in units of the current instruction width `op_size`
+    /// (4 in ARM state, 2 in THUMB). Synchronous exceptions are taken with
+    /// R15 reading as the faulting instruction + 2 instructions, so
+    /// SWI/Undefined/Prefetch-Abort bank the following instruction
+    /// (R15 − op_size) and a Data Abort banks faulting + 2 instructions
+    /// (R15). An IRQ/FIQ is taken between instructions with R15 already at
+    /// the next instruction, so it banks that address + op_size (R15 + op_size).
+    pub const fn link_register_offset(self, op_size: i32) -> i32 {
+        match self {
+            Self::Reset => 0,
+            Self::Undefined | Self::SoftwareInterrupt | Self::PrefetchAbort => -op_size,
+            Self::DataAbort => 0,
+            Self::Irq | Self::Fiq => op_size,
+        }
+    }
+
+    /// The privileged mode the core switches into to handle the exception.
+    pub const fn mode(self) -> Mode {
+        match self {
+            Self::Reset | Self::SoftwareInterrupt => Mode::Supervisor,
+            Self::Undefined => Mode::Undefined,
+            Self::PrefetchAbort | Self::DataAbort => Mode::Abort,
+            Self::Irq => Mode::Irq,
+            Self::Fiq => Mode::Fiq,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Renders an ARM opcode as canonical assembly text. This replaces the
+//! `println!("instruction -> {:?}", instruction)` `Debug` tracing that used
+//! to live in the decode path, so tools and the GDB stub can print a real
+//! listing.
+
+use crate::bitwise::Bits;
+
+/// Disassemble a single 32-bit ARM opcode into an assembly string.
+pub fn disassemble(op_code: u32) -> String {
+    let cond = condition_suffix(op_code);
+
+    if op_code.get_bits(25..=27) == 0b101 {
+        disassemble_branch(op_code, cond)
+    } else if op_code.get_bits(24..=27) == 0b1111 {
+        format!("swi{} #{}", cond, op_code.get_bits(0..=23))
+    } else if op_code.get_bits(26..=27) == 0b01 {
+        disassemble_single_data_transfer(op_code, cond)
+    } else if op_code.get_bits(26..=27) == 0b00 {
+        disassemble_data_processing(op_code, cond)
+    } else {
+        format!(".word 0x{op_code:08x}")
+    }
+}
+
+fn disassemble_branch(op_code: u32, cond: &str) -> String {
+    let link = if op_code.get_bit(24) { "l" } else { "" };
+    // 24-bit signed word offset; the +8 is the ARM pipeline prefetch.
+    let offset = ((op_code.get_bits(0..=23) << 8) as i32 >> 8) * 4 + 8;
+    format!("b{link}{cond} #{offset}")
+}
+
+fn disassemble_data_processing(op_code: u32, cond: &str) -> String {
+    let alu_opcode = op_code.get_bits(21..=24);
+    let set_flags = op_code.get_bit(20);
+    let rd = register_name(op_code.get_bits(12..=15));
+    let rn = register_name(op_code.get_bits(16..=19));
+    let mnemonic = ALU_MNEMONICS[alu_opcode as usize];
+    let operand2 = format_operand2(op_code);
+
+    match alu_opcode {
+        // TST, TEQ, CMP, CMN: no destination, flags implied.
+        0b1000..=0b1011 => format!("{mnemonic}{cond} {rn}, {operand2}"),
+        // MOV, MVN: no first operand.
+        0b1101 | 0b1111 => {
+            let s = if set_flags { "s" } else { "" };
+            format!("{mnemonic}{cond}{s} {rd}, {operand2}")
+        }
+        _ => {
+            let s = if set_flags { "s" } else { "" };
+            format!("{mnemonic}{cond}{s} {rd}, {rn}, {operand2}")
+        }
+    }
+}
+
+fn format_operand2(op_code: u32) -> String {
+    if op_code.get_bit(25) {
+        // Rotated 8-bit immediate.
+        let rotate = op_code.get_bits(8..=11) * 2;
+        let value = op_code.get_bits(0..=7).rotate_right(rotate);
+        format!("#{value}")
+    } else {
+        let rm = register_name(op_code.get_bits(0..=3));
+        let shift = format_shift(op_code);
+        if shift.is_empty() {
+            rm
+        } else {
+            format!("{rm}, {shift}")
+        }
+    }
+}
+
+/// Format the shift applied to a register operand, or the empty string for
+/// the canonical no-op `lsl #0`.
+fn format_shift(op_code: u32) -> String {
+    let shift_name = SHIFT_MNEMONICS[op_code.get_bits(5..=6) as usize];
+    if op_code.get_bit(4) {
+        // Shift amount held in a register.
+        let rs = register_name(op_code.get_bits(8..=11));
+        format!("{shift_name} {rs}")
+    } else {
+        // An immediate amount of 0 encodes LSL#0 (no shift), LSR#32, ASR#32
+        // and RRX respectively.
+        match (op_code.get_bits(7..=11), shift_name) {
+            (0, "lsl") => String::new(),
+            (0, "ror") => "rrx".to_string(),
+            (0, _) => format!("{shift_name} #32"),
+            (amount, _) => format!("{shift_name} #{amount}"),
+        }
+    }
+}
+
+fn disassemble_single_data_transfer(op_code: u32, cond: &str) -> String {
+    let load = if op_code.get_bit(20) { "ldr" } else { "str" };
+    let byte = if op_code.get_bit(22) { "b" } else { "" };
+    let rd = register_name(op_code.get_bits(12..=15));
+    let rn = register_name(op_code.get_bits(16..=19));
+    let up = op_code.get_bit(23);
+    let pre_index = op_code.get_bit(24);
+    let write_back = op_code.get_bit(21);
+
+    let offset = if op_code.get_bit(25) {
+        let rm = register_name(op_code.get_bits(0..=3));
+        let sign = if up { "" } else { "-" };
+        let shift = format_shift(op_code);
+        if shift.is_empty() {
+            format!("{sign}{rm}")
+        } else {
+            format!("{sign}{rm}, {shift}")
+        }
+    } else {
+        let sign = if up { "" } else { "-" };
+        format!("#{sign}{}", op_code.get_bits(0..=11))
+    };
+
+    let address = if pre_index {
+        let bang = if write_back { "!" } else { "" };
+        format!("[{rn}, {offset}]{bang}")
+    } else {
+        format!("[{rn}], {offset}")
+    };
+
+    format!("{load}{cond}{byte} {rd}, {address}")
+}
+
+/// The condition-code suffix for an opcode (empty for AL).
+fn condition_suffix(op_code: u32) -> &'static str {
+    match op_code.get_bits(28..=31) {
+        0b0000 => "eq",
+        0b0001 => "ne",
+        0b0010 => "cs",
+        0b0011 => "cc",
+        0b0100 => "mi",
+        0b0101 => "pl",
+        0b0110 => "vs",
+        0b0111 => "vc",
+        0b1000 => "hi",
+        0b1001 => "ls",
+        0b1010 => "ge",
+        0b1011 => "lt",
+        0b1100 => "gt",
+        0b1101 => "le",
+        // AL is the default and is rendered without a suffix.
+        _ => "",
+    }
+}
+
+/// Register mnemonic, with the `sp`/`lr`/`pc` aliases for R13/R14/R15.
+fn register_name(reg: u32) -> String {
+    match reg {
+        13 => "sp".to_string(),
+        14 => "lr".to_string(),
+        15 => "pc".to_string(),
+        _ => format!("r{reg}"),
+    }
+}
+
+const ALU_MNEMONICS: [&str; 16] = [
+    "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr",
+    "mov", "bic", "mvn",
+];
+
+const SHIFT_MNEMONICS: [&str; 4] = ["lsl", "lsr", "asr", "ror"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn disassemble_mov_immediate() {
+        // MOV R0, #0
+        let op_code = 0b1110_0011_1010_0000_0000_0000_0000_0000;
+        assert_eq!(disassemble(op_code), "mov r0, #0");
+    }
+
+    #[test]
+    fn disassemble_branch() {
+        let op_code = 0b1110_1010_0000_0000_0000_0000_0111_1111;
+        assert_eq!(disassemble(op_code), "b #516");
+    }
+
+    #[test]
+    fn disassemble_ldr_pc_relative() {
+        let op_code = 0b1110_0101_1001_1111_1101_0000_0001_1000;
+        assert_eq!(disassemble(op_code), "ldr sp, [pc, #24]");
+    }
+}
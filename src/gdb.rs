@@ -0,0 +1,260 @@
+//! A minimal GDB remote-serial-protocol (RSP) stub for the [`Arm7tdmi`]
+//! core. It speaks enough of the protocol — `$...#xx` framing with a
+//! two-hex-digit checksum, `+`/`-` acknowledgements, and the `g`/`G`/`m`/
+//! `M`/`s`/`c`/`Z0`/`z0`/`?` packets — for a stock `arm-none-eabi-gdb` to
+//! attach over TCP, set software breakpoints and single-step. A `-` sent by
+//! GDB in answer to a reply is skipped; the reply is not resent.
+//!
+//! The register order in `g`/`G` matches GDB's `org.gnu.gdb.arm.core`
+//! feature: R0–R15 followed by CPSR, each a little-endian 32-bit word.
+
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::arm7tdmi::Arm7tdmi;
+use crate::cpu::Cpu;
+
+/// Number of registers exchanged in the `g`/`G` packets (R0–R15 + CPSR).
+const REGISTER_COUNT: usize = 17;
+
+/// The stop reply used for every trap (SIGTRAP), as GDB expects.
+const TRAP_REPLY: &str = "S05";
+
+/// Wraps a core and the active software breakpoints, serving one GDB
+/// connection at a time over TCP.
+pub struct GdbStub {
+    cpu: Arm7tdmi,
+    breakpoints: HashSet<u32>,
+}
+
+impl GdbStub {
+    pub fn new(cpu: Arm7tdmi) -> Self {
+        Self {
+            cpu,
+            breakpoints: HashSet::new(),
+        }
+    }
+
+    /// Listen on `addr` and serve the first client that connects.
+    pub fn serve<A: ToSocketAddrs>(&mut self, addr: A) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        self.serve_connection(stream)
+    }
+
+    fn serve_connection(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        loop {
+            let Some(packet) = read_packet(&mut stream)? else {
+                return Ok(());
+            };
+            // Acknowledge every well-formed packet before replying.
+            stream.write_all(b"+")?;
+
+            let response = self.handle_packet(&packet);
+            send_packet(&mut stream, &response)?;
+        }
+    }
+
+    /// Dispatch a single RSP packet body and produce the response body.
+    fn handle_packet(&mut self, packet: &str) -> String {
+        match packet.as_bytes().first() {
+            Some(b'?') => TRAP_REPLY.to_string(),
+            Some(b'g') => self.read_registers(),
+            Some(b'G') => self.write_registers(&packet[1..]),
+            Some(b'm') => self.read_memory(&packet[1..]),
+            Some(b'M') => self.write_memory(&packet[1..]),
+            Some(b's') => self.single_step(),
+            Some(b'c') => self.continue_execution(),
+            Some(b'Z') => self.insert_breakpoint(&packet[1..]),
+            Some(b'z') => self.remove_breakpoint(&packet[1..]),
+            // Everything else is reported as unsupported (empty reply).
+            _ => String::new(),
+        }
+    }
+
+    fn read_registers(&self) -> String {
+        let mut out = String::with_capacity(REGISTER_COUNT * 8);
+        for index in 0..16 {
+            out.push_str(&encode_word(self.cpu.register(index)));
+        }
+        out.push_str(&encode_word(self.cpu.cpsr_bits()));
+        out
+    }
+
+    fn write_registers(&mut self, body: &str) -> String {
+        // Each register is 8 hex digits; ignore a short/garbled payload.
+        if body.len() < REGISTER_COUNT * 8 {
+            return "E01".to_string();
+        }
+        for index in 0..16 {
+            let value = decode_word(&body[index * 8..index * 8 + 8]);
+            self.cpu.set_register(index, value);
+        }
+        let cpsr = decode_word(&body[16 * 8..16 * 8 + 8]);
+        self.cpu.set_cpsr_bits(cpsr);
+        "OK".to_string()
+    }
+
+    fn read_memory(&self, body: &str) -> String {
+        let Some((address, length)) = parse_addr_len(body) else {
+            return "E01".to_string();
+        };
+        let mut out = String::with_capacity(length * 2);
+        for offset in 0..length {
+            out.push_str(&format!("{:02x}", self.cpu.read_memory(address + offset)));
+        }
+        out
+    }
+
+    fn write_memory(&mut self, body: &str) -> String {
+        let Some((rest, data)) = body.split_once(':') else {
+            return "E01".to_string();
+        };
+        let Some((address, length)) = parse_addr_len(rest) else {
+            return "E01".to_string();
+        };
+        for offset in 0..length {
+            let byte = decode_byte(&data[offset * 2..offset * 2 + 2]);
+            self.cpu.write_memory(address + offset, byte);
+        }
+        "OK".to_string()
+    }
+
+    fn single_step(&mut self) -> String {
+        self.cpu.step();
+        TRAP_REPLY.to_string()
+    }
+
+    /// Run until the PC reaches a software breakpoint, steps outside
+    /// addressable memory, or a bounded instruction budget is exhausted.
+    /// The bound keeps a `c` with no matching breakpoint from spinning
+    /// forever, and the bounds check stops before the core fetches from
+    /// outside memory, where the bus only reads back zero; either way a
+    /// SIGTRAP is reported so GDB regains control.
+    fn continue_execution(&mut self) -> String {
+        const MAX_STEPS: usize = 1_000_000;
+
+        for _ in 0..MAX_STEPS {
+            // Stop before a fetch would read outside memory, where the bus
+            // returns zero. The check covers the whole aligned word that an
+            // ARM fetch reads (a THUMB fetch reads only half of it).
+            let aligned = self.cpu.register(15) as usize & !0b11;
+            if aligned + 4 > self.cpu.memory_len() {
+                break;
+            }
+            self.cpu.step();
+            if self.breakpoints.contains(&self.cpu.register(15)) {
+                break;
+            }
+        }
+        TRAP_REPLY.to_string()
+    }
+
+    fn insert_breakpoint(&mut self, body: &str) -> String {
+        match parse_breakpoint(body) {
+            Some(address) => {
+                self.breakpoints.insert(address);
+                "OK".to_string()
+            }
+            None => String::new(),
+        }
+    }
+
+    fn remove_breakpoint(&mut self, body: &str) -> String {
+        match parse_breakpoint(body) {
+            Some(address) => {
+                self.breakpoints.remove(&address);
+                "OK".to_string()
+            }
+            None => String::new(),
+        }
+    }
+}
+
+/// Read one `$...#xx` packet, verifying and returning its body. Returns
+/// `Ok(None)` on a clean end-of-stream.
+fn read_packet(stream: &mut TcpStream) -> io::Result<Option<String>> {
+    let mut byte = [0u8; 1];
+
+    // Skip acknowledgements until the start-of-packet marker.
+    loop {
+        if stream.read(&mut byte)? == 0 {
+            return Ok(None);
+        }
+        if byte[0] == b'$' {
+            break;
+        }
+    }
+
+    let mut body = Vec::new();
+    loop {
+        if stream.read(&mut byte)? == 0 {
+            return Ok(None);
+        }
+        if byte[0] == b'#' {
+            break;
+        }
+        body.push(byte[0]);
+    }
+
+    // Verify the two checksum digits against the body; a mismatch is
+    // answered with `-` and the retransmitted packet is read instead.
+    let mut checksum = [0u8; 2];
+    stream.read_exact(&mut checksum)?;
+    let expected = std::str::from_utf8(&checksum)
+        .ok()
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    if expected != Some(body.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))) {
+        stream.write_all(b"-")?;
+        return read_packet(stream);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Frame `body` as `$body#xx` and write it to the stream.
+fn send_packet(stream: &mut TcpStream, body: &str) -> io::Result<()> {
+    let checksum = body.bytes().fold(0u8, u8::wrapping_add);
+    write!(stream, "${}#{:02x}", body, checksum)
+}
+
+fn encode_word(value: u32) -> String {
+    value
+        .to_le_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn decode_word(hex: &str) -> u32 {
+    let mut bytes = [0u8; 4];
+    for (index, chunk) in hex.as_bytes().chunks(2).take(4).enumerate() {
+        bytes[index] = decode_byte(std::str::from_utf8(chunk).unwrap_or("00"));
+    }
+    u32::from_le_bytes(bytes)
+}
+
+fn decode_byte(hex: &str) -> u8 {
+    u8::from_str_radix(hex, 16).unwrap_or(0)
+}
+
+/// Parse an `addr,len` pair (both hexadecimal).
+fn parse_addr_len(body: &str) -> Option<(usize, usize)> {
+    let (addr, len) = body.split_once(',')?;
+    Some((
+        usize::from_str_radix(addr, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+/// Parse a `Z0`/`z0` operand of the form `0,addr,kind`, returning the
+/// breakpoint address. Only software breakpoints (type 0) are handled.
+fn parse_breakpoint(body: &str) -> Option<u32> {
+    let mut parts = body.splitn(3, ',');
+    if parts.next()? != "0" {
+        return None;
+    }
+    let addr = parts.next()?;
+    u32::from_str_radix(addr, 16).ok()
+}
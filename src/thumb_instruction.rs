@@ -0,0 +1,65 @@
+use std::convert::TryFrom;
+
+/// The subset of THUMB (16-bit) instruction formats the core can decode.
+///
+/// Mirrors [`crate::instruction::ArmModeInstruction`] but keyed on the
+/// THUMB format bits (15..=11 and friends) instead of the ARM condition
+/// and class fields.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ThumbModeInstruction {
+    /// Format 1: move shifted register (LSL/LSR/ASR by immediate).
+    MoveShiftedRegister,
+    /// Format 2: add/subtract register or 3-bit immediate.
+    AddSubtract,
+    /// Format 3: move/compare/add/subtract 8-bit immediate.
+    AluImmediate,
+    /// Format 5: hi-register operations and `BX`.
+    HiRegisterOperation,
+    /// Format 6: PC-relative load.
+    PcRelativeLoad,
+    /// Format 16: conditional branch.
+    ConditionalBranch,
+    /// Format 18: unconditional branch.
+    UnconditionalBranch,
+    /// Format 17: software interrupt (`SWI`, `0xDFxx`).
+    SoftwareInterrupt,
+    /// Any encoding the core does not decode, trapped as undefined.
+    Undefined,
+}
+
+impl TryFrom<u16> for ThumbModeInstruction {
+    type Error = String;
+
+    fn try_from(op_code: u16) -> Result<Self, Self::Error> {
+        use ThumbModeInstruction::*;
+
+        // Discriminate on the top bits, most specific formats first.
+        if op_code & 0b1111_1000_0000_0000 == 0b0001_1000_0000_0000 {
+            Ok(AddSubtract)
+        } else if op_code & 0b1110_0000_0000_0000 == 0b0000_0000_0000_0000 {
+            Ok(MoveShiftedRegister)
+        } else if op_code & 0b1110_0000_0000_0000 == 0b0010_0000_0000_0000 {
+            Ok(AluImmediate)
+        } else if op_code & 0b1111_1100_0000_0000 == 0b0100_0100_0000_0000 {
+            Ok(HiRegisterOperation)
+        } else if op_code & 0b1111_1000_0000_0000 == 0b0100_1000_0000_0000 {
+            Ok(PcRelativeLoad)
+        } else if op_code & 0b1111_1111_0000_0000 == 0b1101_1111_0000_0000 {
+            // `0xDFxx`: software interrupt. Shares the `1101` prefix with the
+            // conditional branches, so it must be picked off first or it
+            // would decode as a never-taken branch with condition `1111`.
+            Ok(SoftwareInterrupt)
+        } else if op_code & 0b1111_1111_0000_0000 == 0b1101_1110_0000_0000 {
+            // `0xDExx`: the undefined slot in the conditional-branch grid.
+            Ok(Undefined)
+        } else if op_code & 0b1111_0000_0000_0000 == 0b1101_0000_0000_0000 {
+            Ok(ConditionalBranch)
+        } else if op_code & 0b1111_1000_0000_0000 == 0b1110_0000_0000_0000 {
+            Ok(UnconditionalBranch)
+        } else {
+            // Total like the ARM decode path: unhandled encodings trap as
+            // undefined rather than erroring (and panicking upstream).
+            Ok(Undefined)
+        }
+    }
+}